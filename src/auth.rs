@@ -0,0 +1,82 @@
+use crate::worldmobile::WmClient;
+use axum::{
+    extract::FromRequestParts,
+    http::{request::Parts, StatusCode},
+};
+use secrecy::ExposeSecret;
+use sha2::{Digest, Sha256};
+
+/// Автентифікований виклик разом зі скоупами, які йому дозволені.
+pub struct AuthContext {
+    pub scopes: Vec<String>,
+}
+
+impl AuthContext {
+    /// `scope` дозволено, якщо конфіг містить його точно, або wildcard
+    /// на кшталт `"proxy:*"`, що покриває будь-який `"proxy:..."`.
+    pub fn require_scope(&self, scope: &str) -> Result<(), (StatusCode, String)> {
+        let allowed = self.scopes.iter().any(|granted| {
+            granted == scope
+                || granted == "*"
+                || granted
+                    .strip_suffix(":*")
+                    .map(|prefix| scope.starts_with(prefix) && scope[prefix.len()..].starts_with(':'))
+                    .unwrap_or(false)
+        });
+        if allowed {
+            Ok(())
+        } else {
+            Err((StatusCode::FORBIDDEN, format!("api key is missing required scope: {scope}")))
+        }
+    }
+}
+
+impl FromRequestParts<WmClient> for AuthContext {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(parts: &mut Parts, state: &WmClient) -> Result<Self, Self::Rejection> {
+        let presented = bearer_token(parts).or_else(|| api_key_header(parts)).ok_or((
+            StatusCode::UNAUTHORIZED,
+            "missing Authorization: Bearer or X-Api-Key header".into(),
+        ))?;
+
+        state
+            .settings
+            .api_keys
+            .iter()
+            .find(|candidate| constant_time_eq(candidate.key.expose_secret().as_bytes(), presented.as_bytes()))
+            .map(|candidate| AuthContext { scopes: candidate.scopes.clone() })
+            .ok_or((StatusCode::UNAUTHORIZED, "unknown api key".into()))
+    }
+}
+
+fn bearer_token(parts: &Parts) -> Option<String> {
+    parts
+        .headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|s| s.to_string())
+}
+
+fn api_key_header(parts: &Parts) -> Option<String> {
+    parts
+        .headers
+        .get("x-api-key")
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.to_string())
+}
+
+/// Хешуємо обидва боки до фіксованої довжини перед XOR-порівнянням, щоб
+/// час порівняння не залежав ні від довжини ключів, ні від того, скільки
+/// байтів клієнт вгадав правильно — порівняння довжин саме по собі теж є
+/// каналом витоку.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    let ha = Sha256::digest(a);
+    let hb = Sha256::digest(b);
+    let mut diff = 0u8;
+    for (x, y) in ha.iter().zip(hb.iter()) {
+        diff |= x ^ y;
+    }
+    diff == 0
+}
@@ -0,0 +1,125 @@
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Ключ кешу: шлях апстріму + нормалізований (відсортований) набір query-параметрів.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey(String);
+
+impl CacheKey {
+    pub fn new(path: &str, params: &HashMap<&str, String>) -> Self {
+        let mut pairs: Vec<(&str, &str)> = params.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        pairs.sort_by_key(|(k, _)| *k);
+        // serde_urlencoded percent-encodає кожну пару, тож "&"/"=" всередині
+        // значення параметра не можуть підробити межу між парами (cache-key collision)
+        let query = serde_urlencoded::to_string(&pairs).unwrap_or_default();
+        Self(format!("{path}?{query}"))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    pub value: Value,
+    pub inserted_at: Instant,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// Чи вважати запис свіжим, застарілим (але придатним для conditional GET),
+/// чи відсутнім взагалі.
+pub enum Lookup {
+    Fresh(CacheEntry),
+    Stale(CacheEntry),
+    Miss,
+}
+
+#[derive(Default)]
+pub struct CacheStats {
+    pub hits: AtomicU64,
+    pub misses: AtomicU64,
+    pub revalidations: AtomicU64,
+}
+
+#[derive(Clone)]
+pub struct Cache {
+    entries: Arc<Mutex<HashMap<CacheKey, CacheEntry>>>,
+    stats: Arc<CacheStats>,
+    /// ключі, для яких фонова stale-while-revalidate вже запущена —
+    /// single-flight guard, щоб N одночасних stale-хітів не породжували N апстрім-викликів
+    revalidating: Arc<Mutex<HashSet<CacheKey>>>,
+    ttl: Duration,
+    max_entries: usize,
+}
+
+impl Cache {
+    pub fn new(ttl_ms: u64, max_entries: usize) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            stats: Arc::new(CacheStats::default()),
+            revalidating: Arc::new(Mutex::new(HashSet::new())),
+            ttl: Duration::from_millis(ttl_ms),
+            max_entries,
+        }
+    }
+
+    /// Позначає `key` як таке, що фоново оновлюється. Повертає `true`, якщо саме
+    /// цей виклик першим узяв на себе оновлення (і, отже, має його виконати).
+    pub fn try_begin_revalidation(&self, key: &CacheKey) -> bool {
+        self.revalidating.lock().unwrap().insert(key.clone())
+    }
+
+    /// Знімає позначку після того, як фонове оновлення завершилось (успішно чи ні).
+    pub fn finish_revalidation(&self, key: &CacheKey) {
+        self.revalidating.lock().unwrap().remove(key);
+    }
+
+    pub fn lookup(&self, key: &CacheKey) -> Lookup {
+        let entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some(entry) if entry.inserted_at.elapsed() < self.ttl => Lookup::Fresh(entry.clone()),
+            Some(entry) => Lookup::Stale(entry.clone()),
+            None => Lookup::Miss,
+        }
+    }
+
+    pub fn insert(&self, key: CacheKey, entry: CacheEntry) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.max_entries && !entries.contains_key(&key) {
+            // немає LRU-обліку — просто витісняємо щось довільне, щоб не рости без меж
+            if let Some(evict) = entries.keys().next().cloned() {
+                entries.remove(&evict);
+            }
+        }
+        entries.insert(key, entry);
+    }
+
+    /// Оновити тільки мітку часу (після 304 Not Modified).
+    pub fn touch(&self, key: &CacheKey) {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(entry) = entries.get_mut(key) {
+            entry.inserted_at = Instant::now();
+        }
+    }
+
+    pub fn record_hit(&self) {
+        self.stats.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_miss(&self) {
+        self.stats.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_revalidation(&self) {
+        self.stats.revalidations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stats_snapshot(&self) -> (u64, u64, u64) {
+        (
+            self.stats.hits.load(Ordering::Relaxed),
+            self.stats.misses.load(Ordering::Relaxed),
+            self.stats.revalidations.load(Ordering::Relaxed),
+        )
+    }
+}
@@ -1,16 +1,41 @@
 use dotenvy::dotenv;
+use secrecy::Secret;
 use std::env;
 
 #[derive(Clone, Debug)]
 pub struct Settings {
     pub base_url: String,
-    pub bearer_token: Option<String>,
+    /// обгорнутий, щоб `{:?}` (логи, паніки) ніколи не показав сам токен
+    pub bearer_token: Option<Secret<String>>,
     pub use_stub: bool,
     pub http_timeout_ms: u64,
     pub max_pages: u32,
     pub default_page_size: u32,
     /// Якщо true — при збої апі повернемо stub (щоб UI не пустував)
     pub fail_open: bool,
+    /// скільки тримати відповідь у кеші, перш ніж вважати її застарілою
+    pub cache_ttl_ms: u64,
+    /// максимальна кількість ключів у кеші (найстаріші витісняються)
+    pub cache_max_entries: usize,
+    /// stale-while-revalidate: віддати застарілий запис одразу і оновити кеш у фоні
+    pub cache_swr: bool,
+    /// дедлайн для одного forwarded-запиту в /proxy/*, менший за глобальний TimeoutLayer
+    pub proxy_timeout_ms: u64,
+    /// дозволені CORS origins; None означає "не задано" -> falls back на Any
+    pub cors_allowed_origins: Option<Vec<String>>,
+    /// ключі, якими зовнішні клієнти автентифікуються проти цього проксі
+    pub api_keys: Vec<ApiKeyConfig>,
+    /// скільки разів повторити запит до апстріму на transient-помилках
+    pub max_retries: u32,
+}
+
+/// Один запис з `WM_API_KEYS`: ключ + скоупи, які він авторизує.
+/// `key` обгорнутий у `Secret`, щоб `{:?}` по `Settings`/`WmClient` не видавав
+/// клієнтські credentials так само, як і `bearer_token` вище.
+#[derive(Clone, Debug)]
+pub struct ApiKeyConfig {
+    pub key: Secret<String>,
+    pub scopes: Vec<String>,
 }
 
 impl Settings {
@@ -19,7 +44,7 @@ impl Settings {
 
         let base_url = env::var("WM_BASE_URL")
             .unwrap_or_else(|_| "https://partnerapi.worldmobilelabs.com".to_string());
-        let bearer_token = env::var("WM_BEARER_TOKEN").ok();
+        let bearer_token = env::var("WM_BEARER_TOKEN").ok().map(Secret::new);
         let use_stub = env::var("WM_USE_STUB")
             .ok()
             .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
@@ -34,6 +59,43 @@ impl Settings {
             .ok()
             .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
             .unwrap_or(true); // за замовчуванням: вмикаємо fail-open
+        let cache_ttl_ms = env::var("WM_CACHE_TTL_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(60_000u64);
+        let cache_max_entries = env::var("WM_CACHE_MAX_ENTRIES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(500usize);
+        let cache_swr = env::var("WM_CACHE_SWR")
+            .ok()
+            .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        let proxy_timeout_ms = env::var("WM_PROXY_TIMEOUT_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(10_000u64);
+        let cors_allowed_origins = env::var("WM_CORS_ALLOWED_ORIGINS").ok().map(|v| {
+            v.split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+        });
+        // WM_API_KEYS="key1:countries:read|proxy:*;key2:countries:read"
+        let api_keys = env::var("WM_API_KEYS")
+            .ok()
+            .map(|v| {
+                v.split(';')
+                    .filter(|entry| !entry.trim().is_empty())
+                    .filter_map(|entry| {
+                        let (key, scopes) = entry.split_once(':')?;
+                        let scopes = scopes.split('|').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect();
+                        Some(ApiKeyConfig { key: Secret::new(key.trim().to_string()), scopes })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let max_retries = env::var("WM_MAX_RETRIES").ok().and_then(|v| v.parse().ok()).unwrap_or(3u32);
 
         Self {
             base_url,
@@ -43,6 +105,13 @@ impl Settings {
             max_pages,
             default_page_size,
             fail_open,
+            cache_ttl_ms,
+            cache_max_entries,
+            cache_swr,
+            proxy_timeout_ms,
+            cors_allowed_origins,
+            api_keys,
+            max_retries,
         }
     }
 }
@@ -1,28 +1,36 @@
+mod auth;
+mod cache;
 mod config;
 mod models;
+mod security;
 mod worldmobile;
 
 use axum::{
+    body::Bytes,
     error_handling::HandleErrorLayer,
-    extract::{Query, State},
-    http::StatusCode,
+    extract::{Path, Query, RawQuery, State},
+    http::{HeaderMap, HeaderValue, Method, StatusCode},
     response::IntoResponse,
-    routing::get,
+    routing::{get, on, MethodFilter},
     Json, Router,
 };
-use std::{collections::HashMap, time::Duration};
-use tower::{timeout::TimeoutLayer, BoxError, ServiceBuilder};
-use tower_http::{
-    cors::{Any, CorsLayer},
-    trace::TraceLayer,
+use std::{
+    collections::hash_map::DefaultHasher,
+    collections::HashMap,
+    hash::{Hash, Hasher},
+    time::Duration,
 };
+use tower::{timeout::TimeoutLayer, BoxError, ServiceBuilder};
+use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
 use crate::{
+    auth::AuthContext,
     config::Settings,
-    models::{Country, CountryListResponse, CountryQuery},
+    models::{formatter_for, negotiate_format, Country, CountryListResponse, CountryQuery},
+    security::{build_cors_layer, security_headers},
     worldmobile::{extract_countries, fetch_all_pages, WmClient, WmError},
 };
 
@@ -50,7 +58,7 @@ async fn main() {
     // 1) Timeout (може падати)
     // 2) Trace
     // 3) HandleError (зовнішній до фейлячих шарів — конвертує помилки у HTTP-відповідь)
-    // 4) CORS (найзовнішній — для зручності)
+    // 4) CORS (найзовнішній — для зручності; allowlist з WM_CORS_ALLOWED_ORIGINS, інакше Any)
     let middleware = ServiceBuilder::new()
         .layer(TimeoutLayer::new(Duration::from_secs(20))) // фейлячий шар
         .layer(TraceLayer::new_for_http())
@@ -61,18 +69,18 @@ async fn main() {
                 (StatusCode::INTERNAL_SERVER_ERROR, format!("internal error: {e}"))
             }
         }))
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods(Any)
-                .allow_headers(Any),
-        )
+        .layer(build_cors_layer(&settings))
         .into_inner();
 
     let app = Router::new()
         .route("/health", get(health))
         .route("/countries", get(get_countries))
+        .route(
+            "/proxy/*path",
+            on(MethodFilter::GET.or(MethodFilter::POST), proxy_passthrough),
+        )
         .with_state(client)
+        .layer(axum::middleware::from_fn(security_headers))
         .layer(middleware)
         .merge(SwaggerUi::new("/docs").url("/api-docs/openapi.json", ApiDoc::openapi()));
 
@@ -87,12 +95,18 @@ async fn main() {
 #[utoipa::path(get, path = "/health", tag = "meta")]
 pub async fn health(State(state): State<WmClient>) -> impl IntoResponse {
     let src = if state.settings.use_stub { "stub" } else { "worldmobile" };
+    let (hits, misses, revalidations) = state.cache.stats_snapshot();
     let json = serde_json::json!({
         "status": "ok",
         "base_url": state.settings.base_url,
         "use_stub": state.settings.use_stub,
         "fail_open": state.settings.fail_open,
         "source": src,
+        "cache": {
+            "hits": hits,
+            "misses": misses,
+            "revalidations": revalidations,
+        },
     });
     (StatusCode::OK, Json(json))
 }
@@ -110,9 +124,13 @@ pub async fn health(State(state): State<WmClient>) -> impl IntoResponse {
     )
 )]
 pub async fn get_countries(
+    auth: AuthContext,
     State(client): State<WmClient>,
+    headers: HeaderMap,
     Query(q): Query<CountryQuery>,
-) -> Result<Json<CountryListResponse>, (StatusCode, String)> {
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    auth.require_scope("countries:read")?;
+
     let mut params: HashMap<&str, String> = HashMap::new();
     if let Some(v) = q.country_code { params.insert("country_code", v); }
     if let Some(v) = q.scope { params.insert("scope", v); }
@@ -156,14 +174,78 @@ pub async fn get_countries(
     }.to_string();
 
     let resp = CountryListResponse { count: countries.len(), countries, source: src };
-    Ok(Json(resp))
+
+    let accept = headers.get(axum::http::header::ACCEPT).and_then(|v| v.to_str().ok());
+    let format = negotiate_format(accept, q.format.as_deref());
+    let formatter = formatter_for(format);
+    let body = formatter.render(&resp);
+
+    // ETag рахуємо з рендернутого тіла, а не з проміжного JSON — інакше JSON/CSV/HTML
+    // для тих самих даних отримали б однаковий (і для CSV/HTML — хибний) validator
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+    let etag = format!("\"{:x}\"", hasher.finish());
+    let max_age_secs = client.settings.cache_ttl_ms / 1000;
+
+    let mut out_headers = HeaderMap::new();
+    out_headers.insert("cache-control", HeaderValue::from_str(&format!("public, max-age={max_age_secs}")).unwrap());
+    out_headers.insert("etag", HeaderValue::from_str(&etag).unwrap());
+    // відповідь залежить від Accept (content negotiation) — без цього shared cache
+    // роздасть JSON клієнту, що просив HTML, чи навпаки
+    out_headers.insert("vary", HeaderValue::from_static("Accept"));
+    out_headers.insert(
+        axum::http::header::CONTENT_TYPE,
+        HeaderValue::from_static(formatter.content_type()),
+    );
+
+    Ok((out_headers, body))
+}
+
+/// Генеричний reverse-proxy: `/proxy/*path` релеїть будь-який ендпоінт партнерського API.
+pub async fn proxy_passthrough(
+    auth: AuthContext,
+    State(client): State<WmClient>,
+    method: Method,
+    Path(path): Path<String>,
+    RawQuery(query): RawQuery,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    if let Err(rejection) = auth.require_scope("proxy:forward") {
+        return rejection.into_response();
+    }
+
+    let upstream_method = match reqwest::Method::from_bytes(method.as_str().as_bytes()) {
+        Ok(m) => m,
+        Err(_) => return (StatusCode::BAD_REQUEST, HeaderMap::new(), Bytes::new()).into_response(),
+    };
+    let content_type = headers.get(axum::http::header::CONTENT_TYPE).and_then(|v| v.to_str().ok());
+    let accept = headers.get(axum::http::header::ACCEPT).and_then(|v| v.to_str().ok());
+    let body = if body.is_empty() { None } else { Some(body) };
+
+    match client.forward(upstream_method, &path, query.as_deref(), content_type, accept, body).await {
+        Ok(resp) => {
+            let mut headers = HeaderMap::new();
+            if let Ok(ct) = HeaderValue::from_str(&resp.content_type) {
+                headers.insert(axum::http::header::CONTENT_TYPE, ct);
+            }
+            if let Ok(rid) = HeaderValue::from_str(&resp.request_id) {
+                headers.insert("x-request-id", rid);
+            }
+            (resp.status, headers, resp.body).into_response()
+        }
+        Err(e) => map_err(e).into_response(),
+    }
 }
 
 fn map_err(err: WmError) -> (StatusCode, String) {
     match err {
         WmError::MissingToken => (StatusCode::INTERNAL_SERVER_ERROR, "WM_BEARER_TOKEN is not set".into()),
         WmError::Http(e) => (StatusCode::BAD_GATEWAY, format!("request error: {}", e)),
-        WmError::Upstream { status, body } => (status, body),
+        WmError::Upstream { status, body, .. } => (status, body),
         WmError::BadJson => (StatusCode::BAD_GATEWAY, "invalid JSON from upstream".into()),
+        WmError::NotModified => (StatusCode::BAD_GATEWAY, "upstream sent 304 with no cached entry".into()),
+        WmError::Timeout(_) => (StatusCode::REQUEST_TIMEOUT, "forwarded request timed out".into()),
+        WmError::RetriesExhausted(n) => (StatusCode::BAD_GATEWAY, format!("upstream still failing after {n} retries")),
     }
 }
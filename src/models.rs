@@ -25,4 +25,114 @@ pub struct CountryQuery {
     pub fetch_all: Option<bool>,
     /// бажаний розмір сторінки (якщо підтримується)
     pub page_size: Option<u32>,
+    /// примусово обрати формат відповіді, в обхід `Accept`: json | csv | html
+    pub format: Option<String>,
+}
+
+/// Формат, у якому віддається `CountryListResponse`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentFormat {
+    Json,
+    Csv,
+    Html,
+}
+
+/// Рендерить `CountryListResponse` в конкретний content-type.
+pub trait Formatter {
+    fn content_type(&self) -> &'static str;
+    fn render(&self, resp: &CountryListResponse) -> String;
+}
+
+pub struct JsonFormatter;
+
+impl Formatter for JsonFormatter {
+    fn content_type(&self) -> &'static str {
+        "application/json"
+    }
+
+    fn render(&self, resp: &CountryListResponse) -> String {
+        serde_json::to_string(resp).unwrap_or_else(|_| "{}".to_string())
+    }
+}
+
+pub struct CsvFormatter;
+
+impl Formatter for CsvFormatter {
+    fn content_type(&self) -> &'static str {
+        "text/csv"
+    }
+
+    fn render(&self, resp: &CountryListResponse) -> String {
+        fn escape(field: &str) -> String {
+            if field.contains(',') || field.contains('"') || field.contains('\n') {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        }
+
+        let mut out = String::from("code,name\n");
+        for c in &resp.countries {
+            out.push_str(&escape(&c.code));
+            out.push(',');
+            out.push_str(&escape(c.name.as_deref().unwrap_or("")));
+            out.push('\n');
+        }
+        out
+    }
+}
+
+const HTML_TEMPLATE: &str = r#"<!doctype html>
+<html>
+<head><meta charset="utf-8"><title>World Mobile countries</title></head>
+<body>
+<table border="1" cellpadding="4" cellspacing="0">
+<thead><tr><th>code</th><th>name</th></tr></thead>
+<tbody>
+{{#each countries}}
+<tr><td>{{this.code}}</td><td>{{this.name}}</td></tr>
+{{/each}}
+</tbody>
+</table>
+</body>
+</html>
+"#;
+
+pub struct HtmlFormatter;
+
+impl Formatter for HtmlFormatter {
+    fn content_type(&self) -> &'static str {
+        "text/html"
+    }
+
+    fn render(&self, resp: &CountryListResponse) -> String {
+        let mut hb = handlebars::Handlebars::new();
+        hb.register_escape_fn(handlebars::html_escape);
+        hb.render_template(HTML_TEMPLATE, resp)
+            .unwrap_or_else(|e| format!("<html><body>template error: {e}</body></html>"))
+    }
+}
+
+/// Обирає формат за `?format=` (пріоритет) або за `Accept`, інакше — JSON.
+pub fn negotiate_format(accept: Option<&str>, format_param: Option<&str>) -> ContentFormat {
+    if let Some(f) = format_param {
+        return match f.to_ascii_lowercase().as_str() {
+            "csv" => ContentFormat::Csv,
+            "html" => ContentFormat::Html,
+            _ => ContentFormat::Json,
+        };
+    }
+    match accept {
+        Some(a) if a.contains("text/csv") => ContentFormat::Csv,
+        Some(a) if a.contains("text/html") => ContentFormat::Html,
+        _ => ContentFormat::Json,
+    }
+}
+
+pub fn formatter_for(format: ContentFormat) -> Box<dyn Formatter> {
+    match format {
+        ContentFormat::Json => Box::new(JsonFormatter),
+        ContentFormat::Csv => Box::new(CsvFormatter),
+        ContentFormat::Html => Box::new(HtmlFormatter),
+    }
 }
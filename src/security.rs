@@ -0,0 +1,49 @@
+use crate::config::Settings;
+use axum::{
+    extract::Request,
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use tower_http::cors::{Any, CorsLayer};
+
+/// CORS з явного allowlist, якщо він заданий через `WM_CORS_ALLOWED_ORIGINS`;
+/// інакше — стара поведінка (Any), щоб не ламати локальну розробку.
+pub fn build_cors_layer(settings: &Settings) -> CorsLayer {
+    match &settings.cors_allowed_origins {
+        Some(origins) if !origins.is_empty() => {
+            let parsed: Vec<HeaderValue> = origins
+                .iter()
+                .filter_map(|o| HeaderValue::from_str(o).ok())
+                .collect();
+            CorsLayer::new()
+                .allow_origin(parsed)
+                .allow_methods(Any)
+                .allow_headers(Any)
+        }
+        _ => CorsLayer::new()
+            .allow_origin(Any)
+            .allow_methods(Any)
+            .allow_headers(Any),
+    }
+}
+
+/// Базовий набір security-заголовків для кожної відповіді.
+pub async fn security_headers(req: Request, next: Next) -> Response {
+    let mut resp = next.run(req).await;
+    let headers = resp.headers_mut();
+    headers.insert("x-content-type-options", HeaderValue::from_static("nosniff"));
+    headers.insert("x-frame-options", HeaderValue::from_static("DENY"));
+    headers.insert(
+        "permissions-policy",
+        HeaderValue::from_static("geolocation=(), microphone=(), camera=()"),
+    );
+    // HSTS має сенс лише за TLS-термінацією перед проксі; вмикаємо окремо
+    if std::env::var("WM_FORCE_HSTS").ok().as_deref() == Some("1") {
+        headers.insert(
+            "strict-transport-security",
+            HeaderValue::from_static("max-age=63072000; includeSubDomains"),
+        );
+    }
+    resp
+}
@@ -1,9 +1,17 @@
+use crate::cache::{Cache, CacheEntry, CacheKey, Lookup};
 use crate::config::Settings;
 use axum::http::StatusCode;
-use reqwest::Client;
+use bytes::Bytes;
+use rand::Rng;
+use reqwest::{Client, Method};
+use secrecy::ExposeSecret;
 use serde_json::Value;
 use std::collections::HashMap;
+use std::time::Duration;
 use thiserror::Error;
+use ulid::Ulid;
+
+const PACKAGES_PATH: &str = "/v1/esim-packages/available";
 
 #[derive(Debug, Error)]
 pub enum WmError {
@@ -12,54 +20,299 @@ pub enum WmError {
     #[error("http: {0}")]
     Http(#[from] reqwest::Error),
     #[error("upstream error {status}: {body}")]
-    Upstream { status: StatusCode, body: String },
+    Upstream { status: StatusCode, body: String, retry_after: Option<u64> },
     #[error("invalid json from upstream")]
     BadJson,
+    /// апстрім підтвердив 304 Not Modified, але в нас не було з чим його звірити
+    #[error("upstream returned 304 Not Modified without a cached entry to refresh")]
+    NotModified,
+    #[error("forwarded request timed out after {0:?}")]
+    Timeout(Duration),
+    #[error("upstream still failing after {0} retries")]
+    RetriesExhausted(u32),
+}
+
+/// Результат одного похода в апстрім: або свіже тіло з валідаторами кешу,
+/// або підтвердження, що кешована версія все ще актуальна.
+enum UpstreamResponse {
+    Fresh { value: Value, etag: Option<String>, last_modified: Option<String> },
+    NotModified,
+}
+
+fn is_retryable(err: &WmError) -> bool {
+    match err {
+        WmError::Http(_) => true,
+        WmError::Upstream { status, .. } => status.as_u16() == 429 || status.is_server_error(),
+        _ => false,
+    }
+}
+
+fn retry_after_of(err: &WmError) -> Option<Duration> {
+    match err {
+        WmError::Upstream { retry_after: Some(secs), .. } => Some(Duration::from_secs(*secs)),
+        _ => None,
+    }
+}
+
+/// `base * 2^attempt` (capped), ± jitter до 100ms; поважає `Retry-After`, якщо він є.
+fn backoff_delay(attempt: u32, retry_after: Option<Duration>) -> Duration {
+    if let Some(d) = retry_after {
+        return d;
+    }
+    let base = Duration::from_millis(200);
+    let exponential = base.saturating_mul(1u32 << attempt.min(6));
+    let capped = exponential.min(Duration::from_secs(10));
+    let jitter = Duration::from_millis(rand::thread_rng().gen_range(0..100));
+    capped + jitter
+}
+
+/// Щоб токен ніколи не просочився через тіло помилки апстріму, яке ми
+/// пропускаємо користувачу через `map_err`.
+fn scrub_token(body: &str, token: &str) -> String {
+    if token.is_empty() {
+        body.to_string()
+    } else {
+        body.replace(token, "[REDACTED]")
+    }
 }
 
 #[derive(Clone)]
 pub struct WmClient {
     pub http: Client,
     pub settings: Settings,
+    pub cache: Cache,
 }
 
 impl WmClient {
     pub fn new(settings: Settings) -> Self {
         let http = Client::builder()
             .timeout(std::time::Duration::from_millis(settings.http_timeout_ms))
+            // gzip скорочує трафік, а HTTP/2 переговорюється автоматично по ALPN
+            // поверх TLS, коли зібрано з фічею reqwest "http2"
+            .gzip(true)
             .build()
             .expect("reqwest client");
-        Self { http, settings }
+        let cache = Cache::new(settings.cache_ttl_ms, settings.cache_max_entries);
+        Self { http, settings, cache }
     }
 
-    pub async fn fetch_available_packages(
+    /// Один похід в апстрім з bounded retry на коннект-помилках, таймаутах і 429/5xx,
+    /// з експоненційним backoff + jitter, що поважає `Retry-After`.
+    async fn request_upstream(
         &self,
         params: &HashMap<&str, String>,
-    ) -> Result<Value, WmError> {
-        if self.settings.use_stub {
-            let v: Value = serde_json::from_str(include_str!("./stub.json")).expect("valid stub.json");
-            return Ok(v);
+        conditional: Option<(&Option<String>, &Option<String>)>,
+    ) -> Result<UpstreamResponse, WmError> {
+        let mut attempt = 0u32;
+        loop {
+            match self.request_upstream_once(params, conditional).await {
+                Ok(resp) => return Ok(resp),
+                Err(e) if !is_retryable(&e) => return Err(e),
+                Err(e) if attempt >= self.settings.max_retries => {
+                    tracing::warn!("giving up after {} retries, last error: {}", attempt, e);
+                    return Err(WmError::RetriesExhausted(attempt));
+                }
+                Err(e) => {
+                    let delay = backoff_delay(attempt, retry_after_of(&e));
+                    tracing::warn!("upstream attempt {} failed ({}), retrying in {:?}", attempt + 1, e, delay);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+            }
         }
+    }
+
+    async fn request_upstream_once(
+        &self,
+        params: &HashMap<&str, String>,
+        conditional: Option<(&Option<String>, &Option<String>)>,
+    ) -> Result<UpstreamResponse, WmError> {
         let token = self.settings.bearer_token.clone().ok_or(WmError::MissingToken)?;
-        let url = format!("{}/v1/esim-packages/available", self.settings.base_url.trim_end_matches('/'));
+        let url = format!("{}{}", self.settings.base_url.trim_end_matches('/'), PACKAGES_PATH);
 
-        let resp = self.http
+        let mut req = self.http
             .get(&url)
             .query(&params)
-            .header("Authorization", format!("Bearer {}", token))
-            .header("Accept", "application/json")
-            .send()
-            .await?;
+            .header("Authorization", format!("Bearer {}", token.expose_secret()))
+            .header("Accept", "application/json");
 
+        if let Some((etag, last_modified)) = conditional {
+            if let Some(etag) = etag {
+                req = req.header("If-None-Match", etag);
+            }
+            if let Some(last_modified) = last_modified {
+                req = req.header("If-Modified-Since", last_modified);
+            }
+        }
+
+        let resp = req.send().await?;
         let status = resp.status();
+
+        if status == StatusCode::NOT_MODIFIED {
+            return Ok(UpstreamResponse::NotModified);
+        }
+
+        let etag = resp.headers().get("etag").and_then(|v| v.to_str().ok()).map(|s| s.to_string());
+        let last_modified = resp.headers().get("last-modified").and_then(|v| v.to_str().ok()).map(|s| s.to_string());
+        let retry_after = resp.headers().get("retry-after").and_then(|v| v.to_str().ok()).and_then(|v| v.parse::<u64>().ok());
         let bytes = resp.bytes().await?;
         if !status.is_success() {
-            let body = String::from_utf8_lossy(&bytes).to_string();
-            return Err(WmError::Upstream { status, body });
+            let body = scrub_token(&String::from_utf8_lossy(&bytes), token.expose_secret());
+            return Err(WmError::Upstream { status, body, retry_after });
+        }
+        let value: Value = serde_json::from_slice(&bytes).map_err(|_| WmError::BadJson)?;
+        Ok(UpstreamResponse::Fresh { value, etag, last_modified })
+    }
+
+    pub async fn fetch_available_packages(
+        &self,
+        params: &HashMap<&str, String>,
+    ) -> Result<Value, WmError> {
+        if self.settings.use_stub {
+            let v: Value = serde_json::from_str(include_str!("./stub.json")).expect("valid stub.json");
+            return Ok(v);
+        }
+
+        let key = CacheKey::new(PACKAGES_PATH, params);
+        match self.cache.lookup(&key) {
+            Lookup::Fresh(entry) => {
+                self.cache.record_hit();
+                Ok(entry.value)
+            }
+            Lookup::Stale(entry) if self.settings.cache_swr => {
+                self.cache.record_hit();
+                // віддаємо застарілу відповідь одразу, а кеш оновлюємо у фоні — але лише
+                // одним запитом: якщо оновлення для цього ключа вже в польоті, інші
+                // одночасні stale-хіти просто дочекаються його результату в кеші
+                if self.cache.try_begin_revalidation(&key) {
+                    let this = self.clone();
+                    let params = params.clone();
+                    let key = key.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = this.revalidate(&key, &params, &entry).await {
+                            tracing::warn!("background cache revalidation failed: {}", e);
+                        }
+                        this.cache.finish_revalidation(&key);
+                    });
+                }
+                Ok(entry.value)
+            }
+            Lookup::Stale(entry) => self.revalidate(&key, params, &entry).await,
+            Lookup::Miss => {
+                self.cache.record_miss();
+                match self.request_upstream(params, None).await? {
+                    UpstreamResponse::Fresh { value, etag, last_modified } => {
+                        self.cache.insert(
+                            key,
+                            CacheEntry { value: value.clone(), inserted_at: std::time::Instant::now(), etag, last_modified },
+                        );
+                        Ok(value)
+                    }
+                    // апстрім не повинен відповідати 304 на запит без валідаторів
+                    UpstreamResponse::NotModified => Err(WmError::NotModified),
+                }
+            }
         }
-        let val: Value = serde_json::from_slice(&bytes).map_err(|_| WmError::BadJson)?;
-        Ok(val)
     }
+
+    /// Conditional GET проти кешованого запису: на 304 оновлюємо лише timestamp,
+    /// інакше заміняємо запис новим тілом.
+    async fn revalidate(
+        &self,
+        key: &CacheKey,
+        params: &HashMap<&str, String>,
+        cached: &CacheEntry,
+    ) -> Result<Value, WmError> {
+        match self.request_upstream(params, Some((&cached.etag, &cached.last_modified))).await? {
+            UpstreamResponse::NotModified => {
+                self.cache.touch(key);
+                self.cache.record_revalidation();
+                Ok(cached.value.clone())
+            }
+            UpstreamResponse::Fresh { value, etag, last_modified } => {
+                self.cache.insert(
+                    key.clone(),
+                    CacheEntry { value: value.clone(), inserted_at: std::time::Instant::now(), etag, last_modified },
+                );
+                self.cache.record_revalidation();
+                Ok(value)
+            }
+        }
+    }
+
+    /// Проксіює довільний виклик партнерського API вербатим: статус, content-type
+    /// і тіло відповіді повертаються як є, без жодної проекції в наші моделі.
+    ///
+    /// `raw_query` передається як є (не перепарсюється в мапу), щоб повторювані
+    /// ключі на кшталт `?a=1&a=2` не губилися. `content_type`/`accept` — заголовки
+    /// клієнтського запиту, що проксіюються вище, бо апстрім-API часто не приймає
+    /// тіло без коректного `Content-Type`.
+    pub async fn forward(
+        &self,
+        method: Method,
+        path: &str,
+        raw_query: Option<&str>,
+        content_type: Option<&str>,
+        accept: Option<&str>,
+        body: Option<Bytes>,
+    ) -> Result<ForwardResponse, WmError> {
+        let request_id = Ulid::new().to_string();
+        let span = tracing::info_span!("proxy_forward", request_id = %request_id, %method, path);
+        let _enter = span.enter();
+
+        let token = self.settings.bearer_token.clone().ok_or(WmError::MissingToken)?;
+        let mut url = format!(
+            "{}/{}",
+            self.settings.base_url.trim_end_matches('/'),
+            path.trim_start_matches('/'),
+        );
+        if let Some(q) = raw_query.filter(|q| !q.is_empty()) {
+            url.push('?');
+            url.push_str(q);
+        }
+
+        let mut req = self.http
+            .request(method, &url)
+            .header("Authorization", format!("Bearer {}", token.expose_secret()))
+            .header("X-Request-Id", &request_id);
+        if let Some(content_type) = content_type {
+            req = req.header("Content-Type", content_type);
+        }
+        if let Some(accept) = accept {
+            req = req.header("Accept", accept);
+        }
+        if let Some(body) = body {
+            req = req.body(body);
+        }
+
+        let deadline = Duration::from_millis(self.settings.proxy_timeout_ms);
+        let resp = match tokio::time::timeout(deadline, req.send()).await {
+            Ok(resp) => resp?,
+            Err(_) => {
+                tracing::warn!("forwarded request to {} exceeded {:?} deadline", path, deadline);
+                return Err(WmError::Timeout(deadline));
+            }
+        };
+
+        let status = resp.status();
+        let content_type = resp
+            .headers()
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("application/octet-stream")
+            .to_string();
+        let body = resp.bytes().await?;
+
+        Ok(ForwardResponse { status, content_type, body, request_id })
+    }
+}
+
+/// Відповідь апстріму, передана прозоро через `/proxy/*path`.
+pub struct ForwardResponse {
+    pub status: StatusCode,
+    pub content_type: String,
+    pub body: Bytes,
+    pub request_id: String,
 }
 
 /// універсальний парсер країн